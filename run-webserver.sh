@@ -1,3 +1,4 @@
 #!/bin/sh
 cd ~/www/rust
-ROCKET_ADDRESS=0.0.0.0 ROCKET_LOG_LEVEL=normal ./target/release/logo-test
+# exec, so SIGTERM on redeploys reaches the server and it can shut down gracefully
+ROCKET_ADDRESS=0.0.0.0 ROCKET_LOG_LEVEL=normal exec ./target/release/logo-test
@@ -0,0 +1,173 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! WCAG contrast between a logo's main colors and the background it's
+//! shown on, to catch logos that disappear into the header
+
+use crate::error::Result;
+use crate::png;
+use std::collections::HashMap;
+use std::fmt;
+
+/// WCAG's minimum contrast ratio for graphics, see
+/// <https://www.w3.org/TR/WCAG21/#non-text-contrast>
+pub const MIN_CONTRAST: f64 = 3.0;
+/// How many of the most common colors are checked
+const MAX_COLORS: usize = 3;
+/// Colors covering less of the logo than this are ignored, like
+/// antialiasing at the edges
+const MIN_SHARE: f64 = 0.1;
+/// Pixels less opaque than this are treated as background
+const MIN_ALPHA: u8 = 128;
+
+/// An opaque color
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parse a `#rgb` or `#rrggbb` color
+    pub fn parse_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+        match hex.len() {
+            3 => {
+                let digit = |i: usize| channel(&hex[i..=i]).map(|value| value * 17);
+                Some(Self(digit(0)?, digit(1)?, digit(2)?))
+            }
+            6 => Some(Self(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Relative luminance, from 0 for black to 1 for white
+    pub fn luminance(self) -> f64 {
+        let linear = |channel: u8| {
+            let value = f64::from(channel) / 255.0;
+            if value <= 0.03928 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+
+    /// This color with `alpha` of it over `bg`
+    fn over(self, alpha: u8, bg: Self) -> Self {
+        let blend = |fg: u8, bg: u8| {
+            let alpha = u32::from(alpha);
+            ((u32::from(fg) * alpha + u32::from(bg) * (255 - alpha) + 127) / 255) as u8
+        };
+        Self(
+            blend(self.0, bg.0),
+            blend(self.1, bg.1),
+            blend(self.2, bg.2),
+        )
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Contrast ratio between two colors, from 1:1 to 21:1
+pub fn contrast_ratio(first: Rgb, second: Rgb) -> f64 {
+    let (first, second) = (first.luminance(), second.luminance());
+    let (lighter, darker) = if first > second {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// One of the logo's main colors
+#[derive(Debug)]
+pub struct ColorContrast {
+    pub color: Rgb,
+    /// How much of the logo's visible area is this color, from 0 to 1
+    pub share: f64,
+    /// Contrast ratio against the background
+    pub ratio: f64,
+}
+
+#[derive(Debug)]
+pub struct ContrastReport {
+    pub background: Rgb,
+    /// The logo's main colors, most common first
+    pub colors: Vec<ColorContrast>,
+}
+
+impl ContrastReport {
+    /// Main colors with less contrast than WCAG recommends
+    pub fn low_contrast(&self) -> impl Iterator<Item = &ColorContrast> {
+        self.colors
+            .iter()
+            .filter(|color| color.ratio < MIN_CONTRAST)
+    }
+}
+
+/// Find the logo's main colors in a PNG thumbnail and how well each stands
+/// out against `bg`. Similar colors are grouped together.
+pub fn contrast_report(logo_png: &[u8], bg: Rgb) -> Result<ContrastReport> {
+    let image = png::decode(logo_png)?;
+    // Sums of each group's colors, to average them, and the count
+    let mut groups: HashMap<(u8, u8, u8), ([u64; 3], u64)> = HashMap::new();
+    let mut visible = 0;
+    for [r, g, b, a] in &image.pixels {
+        if *a < MIN_ALPHA {
+            continue;
+        }
+        visible += 1;
+        let color = Rgb(*r, *g, *b).over(*a, bg);
+        let (sums, count) = groups
+            .entry((color.0 >> 4, color.1 >> 4, color.2 >> 4))
+            .or_default();
+        for (sum, channel) in sums.iter_mut().zip([color.0, color.1, color.2]) {
+            *sum += u64::from(channel);
+        }
+        *count += 1;
+    }
+    let mut groups: Vec<_> = groups.into_values().collect();
+    // Break ties by color, so the report doesn't depend on hashing order
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let colors = groups
+        .into_iter()
+        .take(MAX_COLORS)
+        .map(|(sums, count)| {
+            let average = |sum: u64| ((sum + count / 2) / count) as u8;
+            let color = Rgb(average(sums[0]), average(sums[1]), average(sums[2]));
+            ColorContrast {
+                color,
+                share: count as f64 / visible as f64,
+                ratio: contrast_ratio(color, bg),
+            }
+        })
+        .filter(|color| color.share >= MIN_SHARE)
+        .collect();
+    Ok(ContrastReport {
+        background: bg,
+        colors,
+    })
+}
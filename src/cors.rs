@@ -0,0 +1,114 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! CORS for the JSON API, so gadgets on the wikis can call it from the
+//! browser. Only Wikimedia sites (and any configured `extra_domains`) are
+//! allowed, not every origin.
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+use std::io::Cursor;
+
+/// Domains whose sites, and their subdomains, can call the API
+const WIKIMEDIA_DOMAINS: &[&str] = &[
+    "wikipedia.org",
+    "wikimedia.org",
+    "wiktionary.org",
+    "wikibooks.org",
+    "wikinews.org",
+    "wikiquote.org",
+    "wikisource.org",
+    "wikiversity.org",
+    "wikivoyage.org",
+    "wikidata.org",
+    "wikifunctions.org",
+    "mediawiki.org",
+];
+/// How long browsers can cache a preflight response, in seconds
+const MAX_AGE: &str = "86400";
+
+/// Fairing that adds CORS headers to responses from `/api/*`, and answers
+/// preflight `OPTIONS` requests for them
+pub struct Cors {
+    /// Path the tool is served under, see `BASE_PATH`
+    pub base_path: String,
+    /// Third-party wikis that are allowed too
+    pub extra_domains: Vec<String>,
+}
+
+impl Cors {
+    /// Whether requests from this origin, like `https://en.wikipedia.org`,
+    /// can read the API's responses
+    pub fn is_allowed_origin(&self, origin: &str) -> bool {
+        let host = match origin.strip_prefix("https://") {
+            // Origins don't have a path, but can have a port
+            Some(host) if !host.contains('/') => host,
+            _ => return false,
+        };
+        WIKIMEDIA_DOMAINS.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.'))
+        }) || self.extra_domains.iter().any(|domain| domain == host)
+    }
+
+    fn is_api(&self, req: &Request<'_>) -> bool {
+        req.uri()
+            .path()
+            .as_str()
+            .strip_prefix(self.base_path.as_str())
+            .is_some_and(|path| path.starts_with("/api/"))
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if !self.is_api(req) {
+            return;
+        }
+        // There are no OPTIONS routes, so preflight requests got a 404
+        if req.method() == Method::Options {
+            res.set_status(Status::NoContent);
+            res.remove_header("Content-Type");
+            res.set_sized_body(0, Cursor::new(""));
+        }
+        // The response depends on the origin, so caches have to know that
+        res.set_header(Header::new("Vary", "Origin"));
+        let origin = match req.headers().get_one("Origin") {
+            Some(origin) if self.is_allowed_origin(origin) => origin.to_string(),
+            _ => return,
+        };
+        res.set_header(Header::new("Access-Control-Allow-Origin", origin));
+        if req.method() == Method::Options {
+            res.set_header(Header::new("Access-Control-Allow-Methods", "GET, POST"));
+            // /api/batch takes a JSON body
+            res.set_header(Header::new("Access-Control-Allow-Headers", "Content-Type"));
+            res.set_header(Header::new("Access-Control-Max-Age", MAX_AGE));
+        }
+    }
+}
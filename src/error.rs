@@ -0,0 +1,222 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use rocket::http::Status;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub type Result<T, E = LogoTestError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum LogoTestError {
+    #[error("Invalid skin specified")]
+    InvalidSkin,
+    #[error("The wiki's default skin, {0}, isn't supported; please pick a skin")]
+    UnsupportedDefaultSkin(String),
+    #[error("The {0} skin isn't installed on {1}")]
+    SkinNotInstalled(String, String),
+    #[error("Invalid domain specified")]
+    InvalidDomain,
+    #[error("{0} isn't the database name of a Wikimedia wiki")]
+    UnknownWiki(String),
+    #[error("Host must be a test wiki like test.wikipedia.org, or a beta cluster wiki")]
+    InvalidHost,
+    #[error("Mode must be light or dark")]
+    InvalidMode,
+    #[error("Media must be screen or print")]
+    InvalidMedia,
+    #[error("Background color must be a hex color like #rgb or #rrggbb")]
+    InvalidBackground,
+    #[error(
+        "Logo width must be a number from {} to {}",
+        crate::MIN_LOGO_WIDTH,
+        crate::MAX_LOGO_WIDTH
+    )]
+    InvalidLogoWidth,
+    #[error("Logo must be a SVG or PNG")]
+    UnsupportedFileType,
+    #[error("Logo must be a file, like File:Example.svg")]
+    MissingFilePrefix,
+    #[error("Logo names can be at most {} bytes long", crate::MAX_TITLE_LENGTH)]
+    LogoTooLong,
+    #[error("Logo names can't contain # < > [ ] | {{ }} or control characters")]
+    InvalidLogoName,
+    #[error(
+        "Logo URLs must be on a domain that's safe to load images from, like upload.wikimedia.org"
+    )]
+    UntrustedLogoUrl,
+    #[error("That file does not exist on {0}")]
+    FileNotFound(String),
+    #[error("{0} isn't the ID of a file")]
+    MediaInfoNotFound(String),
+    #[error(
+        "Taglines can be at most {} characters long",
+        crate::MAX_TAGLINE_LENGTH
+    )]
+    TaglineTooLong,
+    #[error("Give either a tagline file or tagline text, not both")]
+    ConflictingTagline,
+    #[error("Invalid page title specified")]
+    InvalidPage,
+    #[error("Batches can have at most {} logos", crate::MAX_BATCH_SIZE)]
+    BatchTooLarge,
+    #[error("Invalid form: {0}")]
+    InvalidForm(String),
+    #[error("That short link does not exist")]
+    ShortLinkNotFound,
+    #[error("Couldn't find an unused short code for that test")]
+    ShortCodeTaken,
+    #[error("Couldn't reach {0} — is the domain correct?")]
+    WikiUnreachable(String),
+    #[error("{0} didn't respond with a wiki page — is the domain correct?")]
+    NotWikiPage(String),
+    #[error("The page from {0} couldn't be decoded as text")]
+    UnreadablePage(String),
+    #[error("Couldn't inject the logo CSS; the page from {0} has an unexpected structure")]
+    UnexpectedPage(String),
+    #[error("{0} responded with HTTP {1}")]
+    WikiError(String, u16),
+    #[error("The response from {0} was too large")]
+    ResponseTooLarge(String),
+    #[error("Unable to read the thumbnail: {0}")]
+    InvalidImage(String),
+    #[error("Upstream took too long to respond")]
+    UpstreamTimeout,
+    #[error("The preview took too long to build")]
+    TestTimeout,
+    #[error("Upstream request failed: {0}")]
+    Upstream(#[from] reqwest::Error),
+    #[error("Unexpected response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    #[error("Unable to take a screenshot: {0}")]
+    ScreenshotFailed(String),
+    #[error("The screenshot took too long to render")]
+    ScreenshotTimeout,
+    #[error("Too many screenshots are being taken right now, try again later")]
+    ScreenshotBusy,
+    #[error("Database error: {0}")]
+    Database(#[from] mysql_async::Error),
+    /// An error from a lookup that several requests waited for
+    #[error(transparent)]
+    Shared(Arc<LogoTestError>),
+}
+
+impl LogoTestError {
+    /// Stable identifier for the error, for clients to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidSkin => "invalid-skin",
+            Self::UnsupportedDefaultSkin(_) => "unsupported-default-skin",
+            Self::SkinNotInstalled(_, _) => "skin-not-installed",
+            Self::InvalidDomain => "invalid-domain",
+            Self::UnknownWiki(_) => "unknown-wiki",
+            Self::InvalidHost => "invalid-host",
+            Self::InvalidMode => "invalid-mode",
+            Self::InvalidMedia => "invalid-media",
+            Self::InvalidBackground => "invalid-background",
+            Self::InvalidLogoWidth => "invalid-logo-width",
+            Self::UnsupportedFileType => "unsupported-file-type",
+            Self::MissingFilePrefix => "missing-file-prefix",
+            Self::LogoTooLong => "logo-too-long",
+            Self::InvalidLogoName => "invalid-logo-name",
+            Self::UntrustedLogoUrl => "untrusted-logo-url",
+            Self::FileNotFound(_) => "file-not-found",
+            Self::MediaInfoNotFound(_) => "mediainfo-not-found",
+            Self::TaglineTooLong => "tagline-too-long",
+            Self::ConflictingTagline => "conflicting-tagline",
+            Self::InvalidPage => "invalid-page",
+            Self::BatchTooLarge => "batch-too-large",
+            Self::InvalidForm(_) => "invalid-form",
+            Self::ShortLinkNotFound => "short-link-not-found",
+            Self::ShortCodeTaken => "short-code-taken",
+            Self::WikiUnreachable(_) => "wiki-unreachable",
+            Self::NotWikiPage(_) => "not-wiki-page",
+            Self::UnreadablePage(_) => "unreadable-page",
+            Self::UnexpectedPage(_) => "unexpected-page",
+            Self::WikiError(_, _) => "wiki-error",
+            Self::ResponseTooLarge(_) => "response-too-large",
+            Self::InvalidImage(_) => "invalid-image",
+            Self::UpstreamTimeout => "upstream-timeout",
+            Self::TestTimeout => "test-timeout",
+            Self::Upstream(_) => "upstream",
+            Self::InvalidResponse(_) => "invalid-response",
+            Self::ScreenshotFailed(_) => "screenshot-failed",
+            Self::ScreenshotTimeout => "screenshot-timeout",
+            Self::ScreenshotBusy => "screenshot-busy",
+            Self::Database(_) => "database",
+            Self::Shared(err) => err.code(),
+        }
+    }
+
+    /// HTTP status to respond with
+    pub fn status(&self) -> Status {
+        match self {
+            Self::InvalidSkin
+            | Self::UnsupportedDefaultSkin(_)
+            | Self::SkinNotInstalled(_, _)
+            | Self::InvalidDomain
+            | Self::UnknownWiki(_)
+            | Self::InvalidHost
+            | Self::InvalidMode
+            | Self::InvalidMedia
+            | Self::InvalidBackground
+            | Self::InvalidLogoWidth
+            | Self::UnsupportedFileType
+            | Self::MissingFilePrefix
+            | Self::LogoTooLong
+            | Self::InvalidLogoName
+            | Self::TaglineTooLong
+            | Self::ConflictingTagline
+            | Self::InvalidPage
+            | Self::BatchTooLarge
+            | Self::UntrustedLogoUrl => Status::BadRequest,
+            Self::InvalidForm(_) => Status::UnprocessableEntity,
+            Self::ShortCodeTaken => Status::Conflict,
+            Self::FileNotFound(_) | Self::MediaInfoNotFound(_) | Self::ShortLinkNotFound => {
+                Status::NotFound
+            }
+            Self::WikiUnreachable(_)
+            | Self::NotWikiPage(_)
+            | Self::UnreadablePage(_)
+            | Self::UnexpectedPage(_)
+            | Self::WikiError(_, _)
+            | Self::ResponseTooLarge(_)
+            | Self::InvalidImage(_)
+            | Self::Upstream(_)
+            | Self::InvalidResponse(_) => Status::BadGateway,
+            Self::UpstreamTimeout | Self::TestTimeout => Status::GatewayTimeout,
+            Self::ScreenshotTimeout => Status::GatewayTimeout,
+            Self::ScreenshotBusy => Status::ServiceUnavailable,
+            Self::ScreenshotFailed(_) | Self::Database(_) => Status::InternalServerError,
+            Self::Shared(err) => err.status(),
+        }
+    }
+}
+
+/// Take a shared error back, if nobody else has it anymore
+impl From<Arc<LogoTestError>> for LogoTestError {
+    fn from(err: Arc<LogoTestError>) -> Self {
+        Arc::try_unwrap(err).unwrap_or_else(Self::Shared)
+    }
+}
+
+impl From<url::ParseError> for LogoTestError {
+    fn from(_: url::ParseError) -> Self {
+        Self::InvalidDomain
+    }
+}
@@ -0,0 +1,97 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! The forms that requests are parsed from. Rocket's FromForm derive emits
+//! the removed private_in_public lint alongside the types, which can only
+//! be allowed for the whole module they're in.
+#![allow(renamed_and_removed_lints)]
+
+use rocket::form::{self, FromFormField, ValueField};
+
+/// An on/off option. Besides what Rocket takes for a `bool`, like `true` or
+/// an empty value, it takes `1` and `0`, which is how the tool's own links
+/// set them, see `TestOptions::report_params()`.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq)]
+pub(crate) struct Flag(pub(crate) bool);
+
+impl<'v> FromFormField<'v> for Flag {
+    fn default() -> Option<Self> {
+        Some(Flag(false))
+    }
+
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        match field.value {
+            "1" => Ok(Flag(true)),
+            "0" => Ok(Flag(false)),
+            _ => bool::from_value(field).map(Flag),
+        }
+    }
+}
+
+/// The required `/test` parameters, from a POST body
+#[derive(FromForm)]
+pub(crate) struct TestForm {
+    pub(crate) wiki: String,
+    pub(crate) logo: String,
+    pub(crate) useskin: String,
+}
+
+/// Optional extras for `/test`
+#[derive(Debug, Hash, FromForm)]
+pub(crate) struct TestOptions {
+    /// `light` (the default) or `dark`
+    pub(crate) mode: Option<String>,
+    /// `screen` (the default) or `print`; see `print_media()`
+    pub(crate) media: Option<String>,
+    /// Warn about SVG features that thumbnails lose
+    pub(crate) checksvg: Flag,
+    /// Warn about logo colors that don't stand out from the header
+    pub(crate) checkcontrast: Flag,
+    /// Width to show the logo at, instead of the skin's usual one
+    pub(crate) logowidth: Option<String>,
+    /// Page to preview the logo on, instead of the main page
+    pub(crate) page: Option<String>,
+    /// File repository to look up the logo on, instead of Commons
+    pub(crate) repo: Option<String>,
+    /// Background color to show behind the logo, instead of the skin's
+    pub(crate) bg: Option<String>,
+    /// Approximate the logged-in layout; see `logged_in()`
+    pub(crate) loggedin: Flag,
+    /// Inline the wiki's stylesheets; see `inline_stylesheets()`
+    pub(crate) inline: Flag,
+    /// Overlay alignment guides on the logo; see `guides()`
+    pub(crate) guides: Flag,
+    /// Fetch the page from a test or beta cluster wiki instead, like
+    /// test.wikipedia.org, to see the logo with code that's further along
+    /// the deployment train. `wiki` is still validated.
+    pub(crate) host: Option<String>,
+    /// Remove the page's JavaScript, for a static preview; see
+    /// `html::strip_scripts()`
+    pub(crate) nojs: Flag,
+    /// Show Vector 2022's sticky header and its smaller logo, as if the
+    /// page was scrolled down; see `sticky_header()`
+    pub(crate) sticky: Flag,
+}
+
+/// A `/test` configuration to store under a short code, see `shorten()`
+#[derive(Clone, PartialEq, FromForm)]
+pub(crate) struct ShortenForm {
+    pub(crate) wiki: String,
+    pub(crate) logo: String,
+    pub(crate) useskin: String,
+}
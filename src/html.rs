@@ -0,0 +1,162 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Removing JavaScript from a wiki page, for a static preview. This follows
+//! how HTML is tokenized, closely enough that quoted attributes containing
+//! `>`, comments, and `<script>` inside `<style>` or `<textarea>` are handled
+//! like a browser would, but it isn't a full parser: the page is otherwise
+//! passed through as it is.
+
+/// Elements whose contents are text rather than markup, so what looks like
+/// a tag in them isn't one
+const RAW_TEXT: &[&str] = &["style", "textarea", "title", "xmp", "iframe", "noembed"];
+
+/// Remove `<script>` elements and `on*` event handler attributes. The
+/// contents of `<noscript>` are kept, without the tags, since the browser
+/// would otherwise hide them because it does support scripts.
+pub fn strip_scripts(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(rest.len(), |end| end + 7);
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        // Doctypes and the like, and end tags, which can't have handlers
+        let end_tag = rest.starts_with("</");
+        if rest.starts_with("<!") || rest.starts_with("<?") || end_tag {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            if !(end_tag && tag_name(&rest[2..]).eq_ignore_ascii_case("noscript")) {
+                output.push_str(&rest[..end]);
+            }
+            rest = &rest[end..];
+            continue;
+        }
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // Just a less-than sign
+            output.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+        let (tag, len) = start_tag(rest);
+        let name = tag_name(&rest[1..]).to_ascii_lowercase();
+        rest = &rest[len..];
+        if name == "script" {
+            rest = &rest[raw_text_len(rest, "script")..];
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if name == "noscript" {
+            continue;
+        } else {
+            output.push_str(&tag);
+            if RAW_TEXT.contains(&name.as_str()) {
+                let len = raw_text_len(rest, &name);
+                output.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The tag name at the start of `text`, which is just after the `<` or `</`
+fn tag_name(text: &str) -> &str {
+    let end = text
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// The start tag at the beginning of `html` without any event handlers,
+/// and how long it was originally
+fn start_tag(html: &str) -> (String, usize) {
+    let bytes = html.as_bytes();
+    let mut tag = String::new();
+    let mut pos = 1 + tag_name(&html[1..]).len();
+    tag.push_str(&html[..pos]);
+    loop {
+        // Whitespace and stray slashes between attributes
+        let start = pos;
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+            pos += 1;
+        }
+        if pos >= bytes.len() || bytes[pos] == b'>' {
+            tag.push_str(&html[start..(pos + 1).min(bytes.len())]);
+            return (tag, (pos + 1).min(bytes.len()));
+        }
+        // An attribute name can start with `=`, but not end with one
+        let name_start = pos;
+        pos += 1;
+        while pos < bytes.len() && !b"\t\n\x0c\r />=".contains(&bytes[pos]) {
+            pos += 1;
+        }
+        let name = &html[name_start..pos];
+        let mut value_end = pos;
+        while value_end < bytes.len() && bytes[value_end].is_ascii_whitespace() {
+            value_end += 1;
+        }
+        if value_end < bytes.len() && bytes[value_end] == b'=' {
+            value_end += 1;
+            while value_end < bytes.len() && bytes[value_end].is_ascii_whitespace() {
+                value_end += 1;
+            }
+            match bytes.get(value_end) {
+                Some(quote @ (b'"' | b'\'')) => {
+                    value_end = html[value_end + 1..]
+                        .find(*quote as char)
+                        .map_or(bytes.len(), |end| value_end + end + 2);
+                }
+                _ => {
+                    while value_end < bytes.len()
+                        && !bytes[value_end].is_ascii_whitespace()
+                        && bytes[value_end] != b'>'
+                    {
+                        value_end += 1;
+                    }
+                }
+            }
+            pos = value_end;
+        }
+        // By bytes, since the name can start with a multi-byte character
+        let is_handler = name.len() > 2 && name.as_bytes()[..2].eq_ignore_ascii_case(b"on");
+        if !is_handler {
+            tag.push_str(&html[start..pos]);
+        }
+    }
+}
+
+/// How much of `html` is the raw text of a `name` element, up to (but not
+/// including) its end tag
+fn raw_text_len(html: &str, name: &str) -> usize {
+    let lower = html.to_ascii_lowercase();
+    let end_tag = format!("</{}", name);
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(&end_tag) {
+        let end = from + found;
+        let after = lower.as_bytes().get(end + end_tag.len());
+        if after.is_none_or(|c| c.is_ascii_whitespace() || *c == b'/' || *c == b'>') {
+            return end;
+        }
+        from = end + end_tag.len();
+    }
+    html.len()
+}
@@ -0,0 +1,201 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Interface messages, in the banana i18n format used by other Wikimedia
+//! tools: one JSON file per language in `i18n/`, with `en.json` as the
+//! fallback for anything that hasn't been translated
+
+use lazy_static::lazy_static;
+use rocket::request::{self, FromRequest, Request};
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Directory with the message files
+const I18N_DIR: &str = "i18n";
+/// Language that every message exists in
+const FALLBACK: &str = "en";
+
+/// Messages for a page, keyed by message name
+pub type Messages = BTreeMap<String, String>;
+
+lazy_static! {
+    /// All the messages, loaded at startup
+    pub static ref CATALOG: Catalog =
+        Catalog::load(Path::new(I18N_DIR)).expect("Unable to load messages");
+}
+
+/// Messages for each language, keyed by language code
+#[derive(Default)]
+pub struct Catalog {
+    languages: HashMap<String, Messages>,
+}
+
+impl Catalog {
+    /// Read all the message files in the directory. `qqq.json` documents
+    /// the messages for translators, so it isn't a language to show.
+    fn load(dir: &Path) -> Result<Self, String> {
+        let mut catalog = Self::default();
+        let entries = std::fs::read_dir(dir).map_err(|err| err.to_string())?;
+        for entry in entries {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            let code = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(code) if code != "qqq" && path.extension() == Some("json".as_ref()) => {
+                    code.to_lowercase()
+                }
+                _ => continue,
+            };
+            let text = std::fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            catalog
+                .add(&code, &text)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+        if !catalog.languages.contains_key(FALLBACK) {
+            return Err(format!("{}/{}.json is missing", dir.display(), FALLBACK));
+        }
+        Ok(catalog)
+    }
+
+    /// Add a language from the contents of its message file
+    fn add(&mut self, code: &str, text: &str) -> serde_json::Result<()> {
+        let data: BTreeMap<String, Value> = serde_json::from_str(text)?;
+        let messages = data
+            .into_iter()
+            .filter_map(|(key, value)| match value {
+                // Skip @metadata
+                Value::String(message) => Some((key, message)),
+                _ => None,
+            })
+            .collect();
+        self.languages.insert(code.to_string(), messages);
+        Ok(())
+    }
+
+    /// Pick the language to show: `uselang` if we have it, otherwise the
+    /// best match from the Accept-Language header, otherwise English
+    pub fn negotiate(&self, uselang: Option<&str>, accept_language: Option<&str>) -> String {
+        let mut wanted: Vec<(f32, &str)> = vec![];
+        if let Some(uselang) = uselang {
+            wanted.push((f32::INFINITY, uselang));
+        }
+        if let Some(header) = accept_language {
+            wanted.extend(parse_accept_language(header));
+        }
+        // Stable, so equally weighted languages keep the client's order
+        wanted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        for (_, tag) in wanted {
+            let tag = tag.to_lowercase();
+            // Try the primary language for regional variants, e.g. pt-br
+            // falls back to pt
+            let primary = tag.split('-').next().unwrap_or_default();
+            for code in [tag.as_str(), primary] {
+                if self.languages.contains_key(code) {
+                    return code.to_string();
+                }
+            }
+        }
+        FALLBACK.to_string()
+    }
+
+    /// Messages in the language, with English for any that are missing
+    pub fn messages(&self, code: &str) -> Messages {
+        let mut messages = self.languages.get(FALLBACK).cloned().unwrap_or_default();
+        if let Some(translated) = self.languages.get(code) {
+            messages.extend(translated.clone());
+        }
+        messages
+    }
+}
+
+/// Language ranges from an Accept-Language header, with their weights.
+/// Ranges that are malformed, wildcards or not acceptable (q=0) are dropped.
+fn parse_accept_language(header: &str) -> Vec<(f32, &str)> {
+    header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let weight = match parts.find_map(|part| part.trim().strip_prefix("q=")) {
+                Some(weight) => weight.trim().parse().ok()?,
+                None => 1.0,
+            };
+            if tag.is_empty() || tag == "*" || weight <= 0.0 {
+                None
+            } else {
+                Some((weight, tag))
+            }
+        })
+        .collect()
+}
+
+/// Request guard for the language to show the page in
+pub struct Lang(pub String);
+
+impl Default for Lang {
+    fn default() -> Self {
+        Lang(FALLBACK.to_string())
+    }
+}
+
+impl Lang {
+    /// Add the language and its messages to a template's context
+    pub fn localize<T: Serialize>(&self, page: T) -> Localized<T> {
+        Localized {
+            page,
+            lang: self.0.clone(),
+            msg: CATALOG.messages(&self.0),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Lang {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Infallible> {
+        let uselang = req
+            .query_value::<&str>("uselang")
+            .and_then(|value| value.ok());
+        let accept_language = req.headers().get_one("Accept-Language");
+        request::Outcome::Success(Lang(CATALOG.negotiate(uselang, accept_language)))
+    }
+}
+
+/// Template context with the page's own fields plus `lang` and `msg`
+#[derive(Serialize)]
+pub struct Localized<T> {
+    #[serde(flatten)]
+    page: T,
+    lang: String,
+    msg: Messages,
+}
+
+#[cfg(test)]
+impl Catalog {
+    /// A catalog from message files' contents, keyed by language code
+    pub fn from_files(files: &[(&str, &str)]) -> Self {
+        let mut catalog = Self::default();
+        for (code, text) in files {
+            catalog.add(code, text).unwrap();
+        }
+        catalog
+    }
+}
@@ -0,0 +1,152 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Prometheus metrics, served in the text exposition format at `/metrics`.
+//!
+//! * `logotest_requests_total{route="..."}`: counter of requests handled,
+//!   by route name (`unmatched` for requests that didn't match any route)
+//! * `logotest_upstream_request_duration_seconds{upstream="..."}`: histogram
+//!   of how long requests to Commons (`imageinfo`) and to the wiki being
+//!   tested (`page`, `siteinfo`, `stylesheet`) took, until the response
+//!   headers came back
+
+use lazy_static::lazy_static;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the upstream latency histogram buckets, in seconds
+const BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations in each bucket, not cumulative
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(index) = BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+lazy_static! {
+    static ref REQUESTS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+    static ref UPSTREAM: Mutex<BTreeMap<&'static str, Histogram>> = Mutex::new(BTreeMap::new());
+}
+
+fn observe_upstream(upstream: &'static str, duration: Duration) {
+    UPSTREAM
+        .lock()
+        .unwrap()
+        .entry(upstream)
+        .or_default()
+        .observe(duration.as_secs_f64());
+}
+
+/// Wait for an upstream request, recording how long it took
+pub async fn time_upstream<F: Future>(upstream: &'static str, request: F) -> F::Output {
+    let start = Instant::now();
+    let output = request.await;
+    observe_upstream(upstream, start.elapsed());
+    output
+}
+
+/// Fairing that counts requests by route
+pub struct RequestCounter;
+
+#[rocket::async_trait]
+impl Fairing for RequestCounter {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request counter",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, _res: &mut Response<'r>) {
+        let route = req
+            .route()
+            .and_then(|route| route.name.as_deref())
+            .unwrap_or("unmatched");
+        *REQUESTS
+            .lock()
+            .unwrap()
+            .entry(route.to_string())
+            .or_default() += 1;
+    }
+}
+
+/// Render all metrics in the Prometheus text format
+pub fn render() -> String {
+    let mut text = String::new();
+    text.push_str("# HELP logotest_requests_total Requests handled, by route\n");
+    text.push_str("# TYPE logotest_requests_total counter\n");
+    for (route, count) in REQUESTS.lock().unwrap().iter() {
+        writeln!(
+            text,
+            "logotest_requests_total{{route=\"{}\"}} {}",
+            route, count
+        )
+        .unwrap();
+    }
+    let name = "logotest_upstream_request_duration_seconds";
+    writeln!(text, "# HELP {} Time taken by upstream requests", name).unwrap();
+    writeln!(text, "# TYPE {} histogram", name).unwrap();
+    for (upstream, histogram) in UPSTREAM.lock().unwrap().iter() {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(histogram.buckets.iter()) {
+            cumulative += count;
+            writeln!(
+                text,
+                "{}_bucket{{upstream=\"{}\",le=\"{}\"}} {}",
+                name, upstream, bound, cumulative
+            )
+            .unwrap();
+        }
+        writeln!(
+            text,
+            "{}_bucket{{upstream=\"{}\",le=\"+Inf\"}} {}",
+            name, upstream, histogram.count
+        )
+        .unwrap();
+        writeln!(
+            text,
+            "{}_sum{{upstream=\"{}\"}} {}",
+            name, upstream, histogram.sum
+        )
+        .unwrap();
+        writeln!(
+            text,
+            "{}_count{{upstream=\"{}\"}} {}",
+            name, upstream, histogram.count
+        )
+        .unwrap();
+    }
+    text
+}
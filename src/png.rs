@@ -0,0 +1,290 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Just enough PNG support for diffing thumbnails: decoding the 8-bit,
+//! non-interlaced images that Commons' thumbnailer produces, and encoding
+//! RGBA images
+
+use crate::error::{LogoTestError, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// Largest width or height that's decoded, well above any thumbnail's, so
+/// a hostile header can't make us allocate gigabytes
+pub const MAX_DIMENSION: u32 = 4096;
+
+/// An image as RGBA pixels, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// The pixel at (x, y), or transparent if that's outside the image
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize]
+        } else {
+            [0, 0, 0, 0]
+        }
+    }
+}
+
+fn invalid(reason: &str) -> LogoTestError {
+    LogoTestError::InvalidImage(reason.to_string())
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Undo the per-row filters, returning the raw scanlines without their
+/// filter type bytes
+fn unfilter(data: &[u8], stride: usize, height: usize, bpp: usize) -> Result<Vec<u8>> {
+    let size = stride
+        .checked_mul(height)
+        .ok_or_else(|| invalid("image is too large"))?;
+    if data.len() < size + height {
+        return Err(invalid("image data is truncated"));
+    }
+    let mut out = vec![0u8; size];
+    for y in 0..height {
+        let filter = data[y * (stride + 1)];
+        let row = &data[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let a = if x >= bpp {
+                out[y * stride + x - bpp]
+            } else {
+                0
+            };
+            let b = if y > 0 { out[(y - 1) * stride + x] } else { 0 };
+            let c = if x >= bpp && y > 0 {
+                out[(y - 1) * stride + x - bpp]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("unknown filter type")),
+            };
+            out[y * stride + x] = row[x].wrapping_add(predictor);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let pa = (p - i16::from(a)).abs();
+    let pb = (p - i16::from(b)).abs();
+    let pc = (p - i16::from(c)).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Decode a PNG. Only 8-bit, non-interlaced images are supported, which
+/// covers what Commons serves as thumbnails.
+pub fn decode(bytes: &[u8]) -> Result<Image> {
+    if !bytes.starts_with(SIGNATURE) {
+        return Err(invalid("not a PNG"));
+    }
+    let mut pos = SIGNATURE.len();
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = vec![];
+    while pos + 8 <= bytes.len() {
+        let length = be_u32(&bytes[pos..]) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let end = pos + 8 + length;
+        // Plus the CRC, which isn't checked
+        if end + 4 > bytes.len() {
+            return Err(invalid("chunk is truncated"));
+        }
+        let data = &bytes[pos + 8..end];
+        match kind {
+            b"IHDR" if length == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos = end + 4;
+    }
+    let header = header.ok_or_else(|| invalid("missing header"))?;
+    let width = be_u32(&header[0..]);
+    let height = be_u32(&header[4..]);
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(invalid("image is too large"));
+    }
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if bit_depth != 8 || interlace != 0 {
+        return Err(invalid("only 8-bit, non-interlaced PNGs are supported"));
+    }
+    let channels = match color_type {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return Err(invalid("unknown color type")),
+    };
+    let stride = width as usize * channels;
+    // Each row has a filter type byte too. Anything past that isn't part
+    // of the image, so a zlib bomb stops there.
+    let expected = (stride + 1) * height as usize;
+    let mut data = vec![];
+    ZlibDecoder::new(compressed.as_slice())
+        .take(expected as u64)
+        .read_to_end(&mut data)
+        .map_err(|_| invalid("image data is corrupt"))?;
+    let raw = unfilter(&data, stride, height as usize, channels)?;
+    let pixels = raw
+        .chunks_exact(channels)
+        .map(|p| match color_type {
+            0 => Ok([p[0], p[0], p[0], 255]),
+            4 => Ok([p[0], p[0], p[0], p[1]]),
+            2 => Ok([p[0], p[1], p[2], 255]),
+            6 => Ok([p[0], p[1], p[2], p[3]]),
+            _ => {
+                let index = p[0] as usize;
+                let rgb = palette
+                    .get(index * 3..index * 3 + 3)
+                    .ok_or_else(|| invalid("palette index out of range"))?;
+                let alpha = transparency.get(index).copied().unwrap_or(255);
+                Ok([rgb[0], rgb[1], rgb[2], alpha])
+            }
+        })
+        .collect::<Result<_>>()?;
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32fast::hash(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encode an RGBA image as a PNG
+pub fn encode(image: &Image) -> Vec<u8> {
+    let mut header = vec![];
+    header.extend_from_slice(&image.width.to_be_bytes());
+    header.extend_from_slice(&image.height.to_be_bytes());
+    // 8-bit RGBA, default compression and filtering, not interlaced
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    for row in image.pixels.chunks(image.width.max(1) as usize) {
+        // No filter
+        let mut scanline = vec![0];
+        scanline.extend(row.iter().flatten());
+        encoder.write_all(&scanline).unwrap();
+    }
+    let compressed = encoder.finish().unwrap();
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &compressed);
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PNG with the given header and already compressed image data
+    fn png(header: &[u8], compressed: &[u8]) -> Vec<u8> {
+        let mut bytes = SIGNATURE.to_vec();
+        write_chunk(&mut bytes, b"IHDR", header);
+        write_chunk(&mut bytes, b"IDAT", compressed);
+        write_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![[10, 20, 30, 255]; 6],
+        };
+        image.pixels[4] = [200, 100, 0, 128];
+        let bytes = encode(&image);
+        assert!(bytes.starts_with(b"\x89PNG"));
+        assert_eq!(decode(&bytes).unwrap(), image);
+        assert!(matches!(
+            decode(b"GIF89a"),
+            Err(LogoTestError::InvalidImage(_))
+        ));
+        // A header for something huge is rejected before it's decoded
+        let mut huge = bytes.clone();
+        huge[16..24].copy_from_slice(&[0, 0, 0xff, 0xff, 0, 0, 0xff, 0xff]);
+        assert!(matches!(
+            decode(&huge),
+            Err(LogoTestError::InvalidImage(reason)) if reason == "image is too large"
+        ));
+    }
+
+    #[test]
+    fn test_decode_bomb() {
+        // Far more image data than a 1x1 image has, which is ignored
+        let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+        encoder.write_all(&[0, 7]).unwrap();
+        encoder.write_all(&vec![0; 64 << 20]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let bytes = png(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0], &compressed);
+        let image = decode(&bytes).unwrap();
+        assert_eq!(image.pixels, vec![[7, 7, 7, 255]]);
+    }
+
+    #[test]
+    fn test_decode_filters() {
+        // A 2x2 grayscale image, with the Sub filter on the first row and
+        // Paeth on the second
+        let raw = [1, 10, 5, 4, 20, 30];
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let bytes = png(&[0, 0, 0, 2, 0, 0, 0, 2, 8, 0, 0, 0, 0], &compressed);
+        let image = decode(&bytes).unwrap();
+        let gray = |v| [v, v, v, 255];
+        // Sub: 10, 10+5; Paeth: 10+20 (from above), predictor for the last
+        // pixel is 30 (left) + 15 (above) - 10 (above left) = 35 -> left
+        assert_eq!(image.pixels, vec![gray(10), gray(15), gray(30), gray(60)]);
+    }
+}
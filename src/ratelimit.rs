@@ -0,0 +1,104 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Per-client rate limiting, so this tool can't be used to hammer Commons
+//! and the wikis with requests
+
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How many requests a client can make in a burst
+const CAPACITY: f64 = 30.0;
+/// How long it takes for a client's whole allowance to refill
+const REFILL_PERIOD: Duration = Duration::from_secs(60);
+/// Once this many clients are tracked, forget the ones with full buckets
+const MAX_TRACKED: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * CAPACITY / REFILL_PERIOD.as_secs_f64()).min(CAPACITY);
+        self.updated = now;
+    }
+}
+
+/// Token buckets for each client IP, kept in Rocket's managed state
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Take a token from the client's bucket, returning whether there was
+    /// one to take
+    fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < CAPACITY
+            });
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: CAPACITY,
+            updated: now,
+        });
+        bucket.refill(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Request guard for routes that make upstream requests; fails with 429
+/// once the client has run out of requests
+pub struct RateLimit;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimit {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        let limiter = req.rocket().state::<RateLimiter>();
+        match (limiter, req.client_ip()) {
+            (Some(limiter), Some(ip)) if !limiter.check(ip) => {
+                request::Outcome::Failure((Status::TooManyRequests, ()))
+            }
+            _ => request::Outcome::Success(RateLimit),
+        }
+    }
+}
+
+#[catch(429)]
+pub fn too_many_requests() -> &'static str {
+    "Too many requests, please slow down and try again in a minute"
+}
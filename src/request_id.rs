@@ -0,0 +1,105 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A random ID for every request, to tie what got logged while building a
+//! preview to the request it was for. It's sent back in the `X-Request-Id`
+//! header, so it can be quoted in bug reports.
+//!
+//! tracing events are forwarded to Rocket's logger without the fields of
+//! the spans they're in, so log lines include the ID themselves as
+//! `request_id = %request_id::current()`.
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{self, FromRequest};
+use rocket::{Data, Request, Response};
+use std::future::Future;
+use tracing::Instrument;
+
+/// How many characters IDs are
+const ID_LENGTH: usize = 16;
+/// What `current()` is outside of any request
+const NO_REQUEST: &str = "-";
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The ID of the request being handled
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn generate() -> Self {
+        Self(
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(ID_LENGTH)
+                .map(char::from)
+                .collect(),
+        )
+    }
+
+    /// Run `work` as part of this request, in a `request` span, so what it
+    /// logs can be found by the ID
+    pub async fn scope<F: Future>(&self, work: F) -> F::Output {
+        let span = tracing::info_span!("request", id = %self.0);
+        CURRENT.scope(self.0.clone(), work.instrument(span)).await
+    }
+}
+
+/// The ID of the request whose work is running, see `RequestId::scope()`
+pub fn current() -> String {
+    CURRENT
+        .try_with(|id| id.clone())
+        .unwrap_or_else(|_| NO_REQUEST.to_string())
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(req.local_cache(RequestId::generate).clone())
+    }
+}
+
+/// Fairing that gives each request its ID and sends it back in the
+/// `X-Request-Id` header
+pub struct RequestIds;
+
+#[rocket::async_trait]
+impl Fairing for RequestIds {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request IDs",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        req.local_cache(RequestId::generate);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let id = req.local_cache(RequestId::generate);
+        res.set_header(Header::new("X-Request-Id", id.0.clone()));
+    }
+}
@@ -0,0 +1,99 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Screenshots of previews, taken by a headless Chromium
+
+use crate::error::{LogoTestError, Result};
+use lazy_static::lazy_static;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::tokio::sync::Semaphore;
+use rocket::tokio::{fs, process::Command, time};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Size of the browser window, which is what gets captured
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 800;
+/// How long the page gets to load its styles and images, in the browser's
+/// virtual time, before the screenshot is taken
+const LOAD_BUDGET_MS: u32 = 5000;
+/// How many browsers can be running at once; each one takes a lot of
+/// memory, so requests past this are turned away rather than queued
+const MAX_BROWSERS: usize = 2;
+
+lazy_static! {
+    pub(crate) static ref BROWSERS: Semaphore = Semaphore::new(MAX_BROWSERS);
+}
+
+/// Render the page in the browser and return a PNG screenshot of it. The
+/// page's protocol-relative URLs are resolved against `base`, since it's
+/// loaded from a temporary file rather than the wiki.
+pub async fn take(browser: &str, timeout: Duration, base: &str, html: &str) -> Result<Vec<u8>> {
+    let _permit = BROWSERS
+        .try_acquire()
+        .map_err(|_| LogoTestError::ScreenshotBusy)?;
+    let name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    let dir = std::env::temp_dir().join(format!("logo-test-{}", name));
+    fs::create_dir(&dir).await.map_err(failed)?;
+    let html = html.replacen("<head>", &format!("<head><base href=\"{}\">", base), 1);
+    let result = render(browser, timeout, &dir, &html).await;
+    // Best effort, it's a temporary directory anyway
+    let _ = fs::remove_dir_all(&dir).await;
+    result
+}
+
+async fn render(browser: &str, timeout: Duration, dir: &Path, html: &str) -> Result<Vec<u8>> {
+    let page = dir.join("preview.html");
+    let output = dir.join("screenshot.png");
+    fs::write(&page, html).await.map_err(failed)?;
+    let mut command = Command::new(browser);
+    command
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--hide-scrollbars")
+        .arg(format!("--window-size={},{}", WIDTH, HEIGHT))
+        .arg(format!("--virtual-time-budget={}", LOAD_BUDGET_MS))
+        .arg(format!("--screenshot={}", output.display()))
+        .arg(format!("file://{}", page.display()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // So the browser doesn't outlive a timeout
+        .kill_on_drop(true);
+    let status = time::timeout(timeout, command.status())
+        .await
+        .map_err(|_| LogoTestError::ScreenshotTimeout)?
+        .map_err(failed)?;
+    if !status.success() {
+        return Err(LogoTestError::ScreenshotFailed(format!(
+            "the browser exited with {}",
+            status
+        )));
+    }
+    fs::read(&output).await.map_err(failed)
+}
+
+fn failed(err: std::io::Error) -> LogoTestError {
+    LogoTestError::ScreenshotFailed(err.to_string())
+}
@@ -0,0 +1,116 @@
+/*
+Easily test new logos on Wikimedia sites
+Copyright (C) 2021 Kunal Mehta <legoktm@debian.org>
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU Affero General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU Affero General Public License for more details.
+
+You should have received a copy of the GNU Affero General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Checks for SVG features that Commons' rasterizer handles differently
+//! from browsers, so the thumbnails may not look like the SVG itself
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+
+/// Fonts that are safe to use, see
+/// <https://commons.wikimedia.org/wiki/Help:SVG#Fonts>
+const SAFE_FONTS: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "dejavu sans",
+    "dejavu serif",
+    "dejavu sans mono",
+    "liberation sans",
+    "liberation serif",
+    "liberation mono",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    /// Scripts are stripped
+    Script,
+    /// Images from other sites aren't loaded
+    ExternalImage(String),
+    /// Fonts that aren't installed get substituted
+    NonStandardFont(String),
+    /// SMIL or CSS animations are flattened to one frame
+    Animation,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Script => write!(f, "The SVG contains a script, which thumbnails won't run"),
+            Self::ExternalImage(url) => write!(
+                f,
+                "The SVG embeds the external image {}, which thumbnails won't show",
+                url
+            ),
+            Self::NonStandardFont(font) => write!(
+                f,
+                "The SVG uses the font {}, which thumbnails will replace with another font",
+                font
+            ),
+            Self::Animation => write!(
+                f,
+                "The SVG is animated, but thumbnails and so the logo on the wiki will be static"
+            ),
+        }
+    }
+}
+
+/// Look for features in an SVG that won't survive rasterizing. This is a
+/// quick textual check rather than a full parse, and only advisory.
+pub fn validate_svg_content(bytes: &[u8]) -> Vec<Warning> {
+    lazy_static! {
+        static ref SCRIPT_RE: Regex = Regex::new(r"(?i)<script[\s>]").unwrap();
+        static ref IMAGE_RE: Regex =
+            Regex::new(r#"(?i)<image\b[^>]*\bhref\s*=\s*["'](?P<url>https?:[^"']*)"#).unwrap();
+        // Only the first choice matters, the rest are fallbacks
+        static ref FONT_RE: Regex =
+            Regex::new(r#"(?i)font-family\s*[:=]\s*["']*\s*(?P<font>[^,;"'>}]+)"#).unwrap();
+        static ref SMIL_RE: Regex =
+            Regex::new(r"(?i)<(animate|animateTransform|animateMotion|animateColor|set)[\s/>]")
+                .unwrap();
+        static ref STYLE_RE: Regex = Regex::new(r"(?is)<style\b[^>]*>(?P<css>.*?)</style>").unwrap();
+        static ref KEYFRAMES_RE: Regex = Regex::new(r"(?i)@(-webkit-)?keyframes\b").unwrap();
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let mut warnings = vec![];
+    if SCRIPT_RE.is_match(&text) {
+        warnings.push(Warning::Script);
+    }
+    for caps in IMAGE_RE.captures_iter(&text) {
+        warnings.push(Warning::ExternalImage(caps["url"].to_string()));
+    }
+    for caps in FONT_RE.captures_iter(&text) {
+        let font = caps["font"].trim();
+        if SAFE_FONTS.contains(&font.to_lowercase().as_str()) {
+            continue;
+        }
+        let warning = Warning::NonStandardFont(font.to_string());
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    if SMIL_RE.is_match(&text)
+        || STYLE_RE
+            .captures_iter(&text)
+            .any(|caps| KEYFRAMES_RE.is_match(&caps["css"]))
+    {
+        warnings.push(Warning::Animation);
+    }
+    warnings
+}